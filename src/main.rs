@@ -1,21 +1,34 @@
 use std::{
+    collections::hash_map::DefaultHasher,
     collections::HashMap,
     env,
     error::Error,
     fmt, fs, io,
-    net::SocketAddr,
+    hash::{Hash, Hasher},
+    net::{AddrParseError, SocketAddr},
     path::{Path, PathBuf},
     sync::Arc,
+    time::SystemTime,
 };
 
 use axum::{
-    extract::{self, State},
-    http::StatusCode,
-    response::IntoResponse,
+    body::StreamBody,
+    extract::{self, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
     routing::get,
-    Router,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+use tower_http::{
+    compression::{
+        predicate::{DefaultPredicate, Predicate, SizeAbove},
+        CompressionLayer,
+    },
+    set_header::SetResponseHeaderLayer,
 };
-use serde::Deserialize;
 
 #[derive(Deserialize)]
 struct ServntFile {
@@ -23,6 +36,66 @@ struct ServntFile {
     #[serde(default = "HashMap::new")]
     extensions: HashMap<String, String>,
     paths: AppPaths,
+    #[serde(default)]
+    compression: CompressionConfig,
+    #[serde(default)]
+    server: ServerConfig,
+}
+
+#[derive(Deserialize, Default)]
+struct ServerConfig {
+    host: Option<String>,
+    port: Option<u16>,
+    #[serde(default)]
+    addresses: Vec<String>,
+}
+
+impl ServerConfig {
+    const DEFAULT_HOST: &'static str = "127.0.0.1";
+    const DEFAULT_PORT: u16 = 19518;
+
+    fn resolve_addrs(&self) -> Result<Vec<SocketAddr>, AddrParseError> {
+        if !self.addresses.is_empty() {
+            return self.addresses.iter().map(|address| address.parse()).collect();
+        }
+
+        let host = self.host.as_deref().unwrap_or(Self::DEFAULT_HOST);
+        let port = self.port.unwrap_or(Self::DEFAULT_PORT);
+
+        Ok(vec![format!("{host}:{port}").parse()?])
+    }
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+struct CompressionConfig {
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default = "CompressionConfig::default_min_size")]
+    min_size: u16,
+}
+
+impl CompressionConfig {
+    fn default_min_size() -> u16 {
+        1024
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct NotPartialContent;
+
+impl Predicate for NotPartialContent {
+    fn should_compress<B>(&self, response: &axum::http::Response<B>) -> bool {
+        response.status() != StatusCode::PARTIAL_CONTENT
+    }
+}
+
+#[derive(Clone, Copy)]
+struct CompressionEnabled(bool);
+
+impl Predicate for CompressionEnabled {
+    fn should_compress<B>(&self, _response: &axum::http::Response<B>) -> bool {
+        self.0
+    }
 }
 
 #[derive(Deserialize)]
@@ -36,12 +109,24 @@ struct AppPaths {
     #[serde(default = "AppPaths::default_base")]
     base: String,
     mapped: HashMap<String, String>,
+    #[serde(default)]
+    listing: bool,
+    #[serde(default = "AppPaths::default_index")]
+    index: String,
+    #[serde(default)]
+    show_hidden: bool,
+    #[serde(default)]
+    follow_symlinks: bool,
 }
 
 impl AppPaths {
     fn default_base() -> String {
         "src".to_string()
     }
+
+    fn default_index() -> String {
+        "index.html".to_string()
+    }
 }
 
 fn default_extension_content_types() -> HashMap<String, String> {
@@ -57,7 +142,7 @@ fn default_extension_content_types() -> HashMap<String, String> {
 
 enum FileError {
     IoError(io::Error),
-    UnknownExtension,
+    Forbidden,
 }
 
 impl From<io::Error> for FileError {
@@ -70,15 +155,27 @@ impl fmt::Display for FileError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             FileError::IoError(io_error) => io_error.fmt(f),
-            FileError::UnknownExtension => f.write_str("unknown extension"),
+            FileError::Forbidden => f.write_str("path escapes its root or is a disallowed symlink"),
         }
     }
 }
 
+fn is_text_content_type(content_type: &str) -> bool {
+    content_type.starts_with("text/")
+        || matches!(
+            content_type,
+            "application/json" | "application/javascript" | "image/svg+xml"
+        )
+}
+
 struct ServntState {
     extension_content_types: HashMap<String, String>,
     full_base_path: PathBuf,
     mapped_paths: HashMap<String, PathBuf>,
+    listing: bool,
+    index: String,
+    show_hidden: bool,
+    follow_symlinks: bool,
 }
 
 impl ServntState {
@@ -89,62 +186,262 @@ impl ServntState {
             extension_content_types.insert(extension, content_type);
         }
 
-        let full_base_path = cwd.join(&servnt_file.paths.base).canonicalize()?;
+        let AppPaths {
+            base,
+            mapped,
+            listing,
+            index,
+            show_hidden,
+            follow_symlinks,
+        } = servnt_file.paths;
+
+        let full_base_path = cwd.join(&base).canonicalize()?;
 
-        let mut mapped_paths = HashMap::with_capacity(servnt_file.paths.mapped.len());
+        let mut mapped_paths = HashMap::with_capacity(mapped.len());
 
-        for (matched, mapped) in servnt_file.paths.mapped {
-            mapped_paths.insert(matched, cwd.join(&mapped).canonicalize()?);
+        for (matched, mapped_dir) in mapped {
+            mapped_paths.insert(matched, cwd.join(&mapped_dir).canonicalize()?);
         }
 
         Ok(ServntState {
             extension_content_types,
             full_base_path,
             mapped_paths,
+            listing,
+            index,
+            show_hidden,
+            follow_symlinks,
         })
     }
 
-    fn get_content_type<P>(&self, path: P) -> Result<String, FileError>
+    fn get_content_type<P>(&self, path: P) -> String
     where
         P: AsRef<Path>,
     {
-        self.extension_content_types
-            .get(
-                path.as_ref()
-                    .extension()
-                    .map_or(Err(FileError::UnknownExtension), |extension| {
-                        extension.to_str().ok_or(FileError::UnknownExtension)
-                    })?,
-            )
-            .ok_or(FileError::UnknownExtension)
+        let path = path.as_ref();
+
+        let content_type = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(|extension| self.extension_content_types.get(extension))
             .cloned()
+            .or_else(|| {
+                mime_guess::from_path(path)
+                    .first()
+                    .map(|mime| mime.essence_str().to_string())
+            })
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+
+        if is_text_content_type(&content_type) {
+            format!("{content_type}; charset=utf-8")
+        } else {
+            content_type
+        }
     }
 
-    fn resolve_path<P>(&self, path: P) -> Result<PathBuf, io::Error>
+    async fn resolve_path<P>(&self, path: P) -> Result<PathBuf, FileError>
     where
         P: AsRef<Path>,
     {
         let match_path = Path::new("/").join(&path);
         let mut final_path = None;
+        let mut root = self.full_base_path.as_path();
 
         for (matched, mapped) in &self.mapped_paths {
             if let Ok(stripped_path) = match_path.strip_prefix(matched) {
-                if stripped_path == Path::new("") {
-                    final_path = Some(mapped.clone());
+                root = mapped;
+                final_path = Some(if stripped_path == Path::new("") {
+                    mapped.clone()
                 } else {
-                    final_path = Some(mapped.join(stripped_path));
-                }
+                    mapped.join(stripped_path)
+                });
 
                 break;
             }
         }
 
-        final_path
-            .unwrap_or_else(|| self.full_base_path.join(&path))
-            .canonicalize()
+        let target = final_path.unwrap_or_else(|| self.full_base_path.join(&path));
+
+        if !self.follow_symlinks {
+            let is_symlink = tokio::fs::symlink_metadata(&target)
+                .await
+                .is_ok_and(|metadata| metadata.file_type().is_symlink());
+
+            if is_symlink {
+                return Err(FileError::Forbidden);
+            }
+        }
+
+        let canonical = tokio::fs::canonicalize(&target).await?;
+
+        if !canonical.starts_with(root) {
+            return Err(FileError::Forbidden);
+        }
+
+        Ok(canonical)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum RangeRequest {
+    None,
+    Satisfiable(u64, u64),
+    Unsatisfiable,
+}
+
+fn parse_range(header_value: &str, len: u64) -> RangeRequest {
+    let Some(spec) = header_value.strip_prefix("bytes=") else {
+        return RangeRequest::None;
+    };
+
+    let Some((start_str, end_str)) = spec.split_once('-') else {
+        return RangeRequest::None;
+    };
+
+    if start_str.is_empty() {
+        let Ok(suffix_len) = end_str.parse::<u64>() else {
+            return RangeRequest::None;
+        };
+
+        return if suffix_len == 0 || len == 0 {
+            RangeRequest::Unsatisfiable
+        } else {
+            RangeRequest::Satisfiable(len.saturating_sub(suffix_len), len - 1)
+        };
+    }
+
+    let Ok(start) = start_str.parse::<u64>() else {
+        return RangeRequest::None;
+    };
+
+    let end = if end_str.is_empty() {
+        len.saturating_sub(1)
+    } else {
+        match end_str.parse::<u64>() {
+            Ok(end) => end.min(len.saturating_sub(1)),
+            Err(_) => return RangeRequest::None,
+        }
+    };
+
+    if len == 0 || start > end || start >= len {
+        RangeRequest::Unsatisfiable
+    } else {
+        RangeRequest::Satisfiable(start, end)
     }
 }
 
+fn compute_etag(modified: SystemTime, len: u64) -> String {
+    let mut hasher = DefaultHasher::new();
+    modified.hash(&mut hasher);
+    len.hash(&mut hasher);
+
+    format!("W/\"{:x}\"", hasher.finish())
+}
+
+fn is_not_modified(headers: &HeaderMap, etag: &str, modified: SystemTime) -> bool {
+    if let Some(if_none_match) = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+    {
+        return if_none_match
+            .split(',')
+            .any(|candidate| candidate.trim() == etag || candidate.trim() == "*");
+    }
+
+    if let Some(since) = headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| httpdate::parse_http_date(value).ok())
+    {
+        return modified <= since;
+    }
+
+    false
+}
+
+fn build_not_modified_response(etag: &str, modified: SystemTime) -> Response {
+    (
+        StatusCode::NOT_MODIFIED,
+        [
+            ("ETag".to_string(), etag.to_string()),
+            ("Last-Modified".to_string(), httpdate::fmt_http_date(modified)),
+        ],
+    )
+        .into_response()
+}
+
+async fn build_file_response(
+    file_path: &Path,
+    content_type: String,
+    len: u64,
+    etag: &str,
+    modified: SystemTime,
+    headers: &HeaderMap,
+) -> Result<Response, io::Error> {
+    let range_request = headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| parse_range(value, len))
+        .unwrap_or(RangeRequest::None);
+
+    let last_modified = httpdate::fmt_http_date(modified);
+
+    if let RangeRequest::Unsatisfiable = range_request {
+        return Ok((
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            [
+                ("Content-Range".to_string(), format!("bytes */{len}")),
+                ("ETag".to_string(), etag.to_string()),
+                ("Last-Modified".to_string(), last_modified.clone()),
+            ],
+        )
+            .into_response());
+    }
+
+    let mut file = tokio::fs::File::open(file_path).await?;
+
+    Ok(match range_request {
+        RangeRequest::Satisfiable(start, end) => {
+            file.seek(io::SeekFrom::Start(start)).await?;
+            let body_len = end - start + 1;
+            let body = StreamBody::new(ReaderStream::new(file.take(body_len)));
+
+            (
+                StatusCode::PARTIAL_CONTENT,
+                [
+                    ("Content-Type".to_string(), content_type),
+                    (
+                        "Content-Range".to_string(),
+                        format!("bytes {start}-{end}/{len}"),
+                    ),
+                    ("Content-Length".to_string(), body_len.to_string()),
+                    ("Accept-Ranges".to_string(), "bytes".to_string()),
+                    ("ETag".to_string(), etag.to_string()),
+                    ("Last-Modified".to_string(), last_modified),
+                ],
+                body,
+            )
+                .into_response()
+        }
+        _ => {
+            let body = StreamBody::new(ReaderStream::new(file));
+
+            (
+                StatusCode::OK,
+                [
+                    ("Content-Type".to_string(), content_type),
+                    ("Content-Length".to_string(), len.to_string()),
+                    ("Accept-Ranges".to_string(), "bytes".to_string()),
+                    ("ETag".to_string(), etag.to_string()),
+                    ("Last-Modified".to_string(), last_modified),
+                ],
+                body,
+            )
+                .into_response()
+        }
+    })
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     let cwd = env::current_dir()?;
@@ -155,47 +452,252 @@ async fn main() -> Result<(), Box<dyn Error>> {
         servnt_file.app.name, servnt_file.app.version
     );
 
+    let compression = servnt_file.compression;
+    let addrs = servnt_file.server.resolve_addrs()?;
     let state = Arc::new(ServntState::new(&cwd, servnt_file)?);
 
-    let app = Router::new()
+    let mut app = Router::new()
         .route("/", get(get_root_index))
         .route("/*desired", get(get_path))
-        .with_state(state);
+        .with_state(state)
+        .layer(
+            CompressionLayer::new().compress_when(
+                DefaultPredicate::new()
+                    .and(SizeAbove::new(compression.min_size))
+                    .and(NotPartialContent)
+                    .and(CompressionEnabled(compression.enabled)),
+            ),
+        );
+
+    if compression.enabled {
+        // Don't rely on CompressionLayer to set this itself; stamp it
+        // explicitly so shared proxies don't cache a negotiated response
+        // under the wrong key.
+        app = app.layer(SetResponseHeaderLayer::if_not_present(
+            header::VARY,
+            HeaderValue::from_static("Accept-Encoding"),
+        ));
+    }
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], 19518));
+    let mut listeners = tokio::task::JoinSet::new();
 
-    axum::Server::bind(&addr)
-        .serve(app.into_make_service())
-        .await?;
+    for addr in addrs {
+        let app = app.clone();
+
+        listeners.spawn(async move {
+            let server = axum::Server::bind(&addr).serve(app.into_make_service());
+            println!("Listening on http://{}", server.local_addr());
+            server.await
+        });
+    }
+
+    while let Some(result) = listeners.join_next().await {
+        result??;
+    }
 
     Ok(())
 }
 
-async fn get_file(state: &ServntState, path: &str) -> Result<impl IntoResponse, impl IntoResponse> {
-    state
-        .resolve_path(path)
-        .map_err(FileError::IoError)
-        .and_then(|file_path| {
-            Ok(state
-                .get_content_type(&file_path)
-                .map(|content_type| (content_type, file_path))?)
-        })
-        .and_then(|(content_type, file_path)| {
-            Ok(([("Content-Type", content_type)], fs::read(file_path)?))
-        })
-        .or_else(|error| {
+enum OutputFormat {
+    Html,
+    Json,
+}
+
+impl OutputFormat {
+    fn negotiate(headers: &HeaderMap, query_format: Option<&str>) -> OutputFormat {
+        let wants_json = query_format.is_some_and(|format| format.eq_ignore_ascii_case("json"))
+            || headers
+                .get(header::ACCEPT)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.contains("application/json"));
+
+        if wants_json {
+            OutputFormat::Json
+        } else {
+            OutputFormat::Html
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct DirEntryInfo {
+    name: String,
+    size: u64,
+    is_dir: bool,
+    modified: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct ListingQuery {
+    format: Option<String>,
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn dir_href_base(request_path: &str) -> String {
+    let trimmed = request_path.trim_matches('/');
+
+    if trimmed.is_empty() {
+        "/".to_string()
+    } else {
+        format!("/{trimmed}/")
+    }
+}
+
+fn render_html_listing(request_path: &str, entries: &[DirEntryInfo]) -> String {
+    let label = escape_html(request_path);
+    let href_base = dir_href_base(request_path);
+    let mut rows = String::new();
+
+    for entry in entries {
+        let href = if entry.is_dir {
+            format!("{href_base}{}/", entry.name)
+        } else {
+            format!("{href_base}{}", entry.name)
+        };
+
+        rows.push_str(&format!(
+            "<tr><td><a href=\"{href}\">{name}{suffix}</a></td><td>{size}</td><td>{modified}</td></tr>\n",
+            href = escape_html(&href),
+            name = escape_html(&entry.name),
+            suffix = if entry.is_dir { "/" } else { "" },
+            size = if entry.is_dir {
+                "-".to_string()
+            } else {
+                entry.size.to_string()
+            },
+            modified = entry.modified.as_deref().unwrap_or("-"),
+        ));
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Index of {label}</title></head>\n<body>\n<h1>Index of {label}</h1>\n<table>\n<thead><tr><th>Name</th><th>Size</th><th>Last Modified</th></tr></thead>\n<tbody>\n{rows}</tbody>\n</table>\n</body>\n</html>\n"
+    )
+}
+
+async fn build_listing_response(
+    state: &ServntState,
+    dir_path: &Path,
+    request_path: &str,
+    headers: &HeaderMap,
+    format: Option<&str>,
+) -> Result<Response, io::Error> {
+    let mut entries = Vec::new();
+    let mut read_dir = tokio::fs::read_dir(dir_path).await?;
+
+    while let Some(entry) = read_dir.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        if !state.show_hidden && name.starts_with('.') {
+            continue;
+        }
+
+        let metadata = entry.metadata().await?;
+
+        entries.push(DirEntryInfo {
+            name,
+            size: metadata.len(),
+            is_dir: metadata.is_dir(),
+            modified: metadata.modified().ok().map(httpdate::fmt_http_date),
+        });
+    }
+
+    entries.sort_by(|a, b| match (a.is_dir, b.is_dir) {
+        (true, false) => std::cmp::Ordering::Less,
+        (false, true) => std::cmp::Ordering::Greater,
+        _ => a.name.cmp(&b.name),
+    });
+
+    let response = match OutputFormat::negotiate(headers, format) {
+        OutputFormat::Json => Json(entries).into_response(),
+        OutputFormat::Html => (
+            [("Content-Type", "text/html; charset=utf-8")],
+            render_html_listing(request_path, &entries),
+        )
+            .into_response(),
+    };
+
+    Ok(response)
+}
+
+async fn serve_path(
+    state: &ServntState,
+    path: &str,
+    headers: &HeaderMap,
+    format: Option<&str>,
+) -> Result<Response, FileError> {
+    let resolved_path = state.resolve_path(path).await?;
+    let metadata = tokio::fs::metadata(&resolved_path).await?;
+
+    let file_path = if metadata.is_dir() {
+        let index_path = resolved_path.join(&state.index);
+        let index_metadata = tokio::fs::metadata(&index_path).await;
+
+        if index_metadata.is_ok_and(|metadata| metadata.is_file()) {
+            index_path
+        } else if state.listing {
+            return Ok(build_listing_response(state, &resolved_path, path, headers, format).await?);
+        } else {
+            return Err(FileError::IoError(io::Error::new(
+                io::ErrorKind::NotFound,
+                "directory has no index file and listing is disabled",
+            )));
+        }
+    } else {
+        resolved_path
+    };
+
+    let content_type = state.get_content_type(&file_path);
+    let metadata = tokio::fs::metadata(&file_path).await?;
+    let len = metadata.len();
+    let modified = metadata.modified()?;
+    let etag = compute_etag(modified, len);
+
+    if is_not_modified(headers, &etag, modified) {
+        return Ok(build_not_modified_response(&etag, modified));
+    }
+
+    Ok(build_file_response(&file_path, content_type, len, &etag, modified, headers).await?)
+}
+
+async fn get_file(state: &ServntState, path: &str, headers: &HeaderMap, format: Option<&str>) -> Response {
+    serve_path(state, path, headers, format)
+        .await
+        .unwrap_or_else(|error| {
             eprintln!("{error}");
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+
+            match error {
+                FileError::Forbidden => StatusCode::FORBIDDEN.into_response(),
+                FileError::IoError(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            }
         })
 }
 
 async fn get_path(
     extract::Path(desired_path): extract::Path<String>,
     State(state): State<Arc<ServntState>>,
+    Query(listing_query): Query<ListingQuery>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
-    get_file(&state, &desired_path).await
+    get_file(
+        &state,
+        &desired_path,
+        &headers,
+        listing_query.format.as_deref(),
+    )
+    .await
 }
 
-async fn get_root_index(State(state): State<Arc<ServntState>>) -> impl IntoResponse {
-    get_file(&state, "index.html").await
+async fn get_root_index(
+    State(state): State<Arc<ServntState>>,
+    Query(listing_query): Query<ListingQuery>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    get_file(&state, "", &headers, listing_query.format.as_deref()).await
 }